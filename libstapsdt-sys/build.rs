@@ -11,36 +11,101 @@ use glob::glob;
 fn main() {
     println!("cargo:rerun-if-changed=build.rs");
 
-    if env::var("CARGO_CFG_TARGET_ARCH").unwrap() != "x86_64" {
-        panic!("libstapsdt is only supported on 64-bit Intel x86");
+    // libstapsdt wraps a Linux/x86_64-only subsystem (SystemTap USDT).  On any other target there's
+    // nothing to build: don't panic, since that would break every workspace that merely depends on
+    // `tracers` transitively and happens to cross-compile for, say, aarch64 or macOS.  Instead, bail
+    // out cleanly and tell dependent crates via our `links` metadata that the native lib is
+    // unavailable here, so they can fall back to a no-op tracing implementation.
+    if env::var("CARGO_CFG_TARGET_ARCH").unwrap() != "x86_64"
+        || env::var("CARGO_CFG_TARGET_OS").unwrap() != "linux"
+    {
+        println!("cargo:unsupported=1");
+        return;
     }
 
-    if env::var("CARGO_CFG_TARGET_OS").unwrap() != "linux" {
-        panic!("libstapsdt is only supported on Linux");
+    // A fully static executable (musl targets, or any target where `crt-static` is on) can't
+    // tolerate a `.so` anywhere in its dependency graph, and static linking of libstapsdt's own
+    // dependencies (libelf, libdl) is the only thing that works in that mode.  Detect that case
+    // and force static linking regardless of what `LIBSTAPSDT_DYNAMIC` says.
+    let crt_static = is_crt_static();
+
+    // The documented, resolver-visible way to pick a linking mode is the `static_link`/`dynamic_link`
+    // Cargo features (static by default), since unlike an env var they participate in Cargo's
+    // dependency resolution and so can't silently disagree between crates in the same build.  If
+    // feature unification across the dependency graph ends up enabling both, that's a build
+    // configuration error and we say so rather than guessing.
+    //
+    // `LIBSTAPSDT_DYNAMIC` is kept working as an escape hatch for compatibility with existing
+    // setups, but new configuration should go through the features.
+    if cfg!(feature = "static_link") && cfg!(feature = "dynamic_link") {
+        panic!(
+            "libstapsdt-sys: both the `static_link` and `dynamic_link` features are enabled. \
+             Exactly one linking mode must be selected; check for feature unification across \
+             your dependency graph."
+        );
     }
 
-    // By default this statically links to libstapsdt.  That can be overriden
-    let wants_dynamic = env::var("LIBSTAPSDT_DYNAMIC").is_ok();
+    let wants_dynamic =
+        (cfg!(feature = "dynamic_link") || env::var("LIBSTAPSDT_DYNAMIC").is_ok()) && !crt_static;
     let statik = if wants_dynamic { "" } else { "static=" };
     let libext = if wants_dynamic { "so" } else { "a" };
 
     // It's unlikely pkg_config knows about this, since the library's own deb package doesn't
-    // register the library with pkg-config.  However it doesn't hurt to try.
-    if try_pkgconfig("libstapsdt", wants_dynamic).is_ok() {
+    // register the library with pkg-config.  However it doesn't hurt to try -- except when we're
+    // cross-compiling (pkg-config would find the *host's* libstapsdt, not the target's) or when
+    // static linking was requested (distro-packaged static libs are rare and inconsistent, so the
+    // vendored source build below is the only reliable path).  In either of those cases skip
+    // straight to building from source.
+    if !is_cross_compiling() && wants_dynamic && try_pkgconfig("libstapsdt", wants_dynamic).is_ok()
+    {
         // This is an unlikely code path but pkg_config found the dependencies and printed
         // them out for cargo to read already
         return;
     }
 
+    // In the static-executable case, libstapsdt, libelf and libdl all reference each other, and a
+    // linker that only makes one pass over its inputs (the GNU default) will fail with "undefined
+    // reference" errors depending on the order these end up on the command line.  Wrapping them in
+    // `--start-group`/`--end-group` makes the linker keep re-scanning the group until everything
+    // resolves, so link order stops mattering.  This is a GNU ld/gold/lld extension; MSVC's linker
+    // doesn't understand it (and doesn't need it, since it isn't a concern on this Linux-only crate).
+    // `cargo:rustc-link-arg` and `cargo:rustc-link-lib` are independent instructions cargo collects
+    // separately and is free to interleave however it likes on the final link line, so printing
+    // `--start-group`/`--end-group` as link-args around `rustc-link-lib` lines does not reliably
+    // bracket them. When the group is needed, emit every lib it must cover as `-l` flags via
+    // `rustc-link-arg` too, so they land inside the same contiguous argument list as the group
+    // markers.
+    let use_linker_group = crt_static && env::var("CARGO_CFG_TARGET_ENV").as_deref() != Ok("msvc");
+    // `crt_static` forces `wants_dynamic` off above, so every lib the group needs to cover is
+    // static whenever the group itself is in play.
+    let link_lib = |lib: &str| {
+        if use_linker_group {
+            println!("cargo:rustc-link-arg=-Wl,-l{}", lib);
+        } else {
+            println!("cargo:rustc-link-lib={}{}", statik, lib);
+        }
+    };
+
+    if use_linker_group {
+        println!("cargo:rustc-link-arg=-Wl,--start-group");
+    }
+
     // no matter what, tell cargo to link with this libstapd library, either the one that's
     // installed or the one we'll build below
-    println!("cargo:rustc-link-lib={}{}", statik, "stapsdt");
+    link_lib("stapsdt");
 
     // In the dynamic link case, dependencies are resolved at runtime, but in the static case dependencies
     // must be resolved now.  That means we must also resolve the libstapsdt's dependencies libelf and libdl,
-    // and both of those must be static also.
+    // and both of those must be static also.  We don't probe pkg-config for libelf here, for the same
+    // reason we skip it for libstapsdt above in the static case: libelf and libdl are linked by name
+    // right here, and the `DEP_ELF_INCLUDE` handling below only supplies libelf's include directory.
     if !wants_dynamic {
-        let _ = try_pkgconfig("libelf", false);
+        link_lib("elf");
+        link_lib("dl");
+    }
+
+    if use_linker_group {
+        println!("cargo:rustc-link-arg=-Wl,--end-group");
     }
 
     //The makefile for libstapsdt is mercifully simple, and since it's wrapping a Linux-only
@@ -112,30 +177,50 @@ fn main() {
     .compile(&format!("libstapsdt.{}", libext));
 }
 
+/// Returns true if we're building a fully static executable: either `crt-static` is one of the
+/// enabled target features (the way Rust spells "statically link the C runtime", notably true by
+/// default on `*-musl` targets), or the target environment is `musl` outright.
+fn is_crt_static() -> bool {
+    let has_crt_static_feature = env::var("CARGO_CFG_TARGET_FEATURE")
+        .map(|features| features.split(',').any(|f| f == "crt-static"))
+        .unwrap_or(false);
+
+    has_crt_static_feature || env::var("CARGO_CFG_TARGET_ENV").as_deref() == Ok("musl")
+}
+
+/// Returns true if the build host isn't the same machine the compiled code will run on.  In that
+/// case pkg-config would be reporting paths and libs for the *host's* libraries, which are at best
+/// useless and at worst actively wrong for the target we're linking.
+fn is_cross_compiling() -> bool {
+    env::var("HOST").ok() != env::var("TARGET").ok()
+}
+
 fn try_pkgconfig(
     package: &str,
     wants_dynamic: bool,
 ) -> Result<pkg_config::Library, pkg_config::Error> {
     let pkg = pkg_config::Config::new()
         .statik(!wants_dynamic)
+        // Let pkg-config itself emit the `cargo:` metadata lines instead of looping over the paths
+        // it reports and re-printing them ourselves -- `cargo_metadata(true)` already covers
+        // `rustc-link-lib`/`rustc-link-search` for every lib and path pkg-config found, and a second,
+        // manual loop over the same `pkg.libs`/`pkg.link_paths` would just duplicate those lines.
+        // `print_system_libs(false)` also needs to be the only place system dirs like `/usr/lib` get
+        // decided on -- doing it again by hand here would silently re-include exactly what that flag
+        // is meant to suppress, which can hijack the linking of other native libraries built later in
+        // the same `cargo build`.
+        .cargo_metadata(true)
+        .print_system_libs(false)
         .probe(package)?;
-    let cargo_link_lib = |lib: &str| {
-        let statik = if wants_dynamic { "" } else { "static=" };
-        println!("cargo:rustc-link-lib={}{}", statik, lib);
-    };
 
+    // `cargo:include` is our own `links = "stapsdt"` metadata key, not something pkg-config's
+    // `cargo_metadata(true)` prints on our behalf, so it's still our job to emit it from
+    // `pkg.include_paths` -- this is the one piece of the old manual loop that wasn't duplicating
+    // `cargo_metadata(true)`'s own output.
     for path in &pkg.include_paths {
         println!("cargo:include={}", path.display());
     }
 
-    for path in &pkg.link_paths {
-        println!("cargo:rustc-link-search=native={}", path.display());
-    }
-
-    for lib in &pkg.libs {
-        cargo_link_lib(lib);
-    }
-
     Ok(pkg)
 }
 
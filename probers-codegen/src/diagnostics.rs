@@ -0,0 +1,8 @@
+//! Span-accurate compile-time diagnostics for the `#[prober]`/`probe!` macros.
+//!
+//! This used to be its own, separately-maintained copy of the exact same `Diagnostic`/`Diagnostics`
+//! pair `tracers-codegen` has, down to an identical rationale doc-comment -- the two had drifted
+//! apart only in that this one lacked `with_note`, not in anything that genuinely needed to differ
+//! between the legacy `probers` macros and the `tracers` rewrite. Re-exporting the one canonical
+//! copy means a future feature (or bugfix) to either only has to happen once.
+pub(crate) use tracers_codegen::diagnostics::{Diagnostic, Diagnostics};
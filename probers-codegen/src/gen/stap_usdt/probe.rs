@@ -0,0 +1,117 @@
+//! Generates the per-probe code for the SystemTap/USDT backend: the trait method that actually
+//! fires a probe, plus the `ProviderProbe` struct member and registration code `provider.rs` wires
+//! into the provider impl module it generates.
+use crate::probe::ProbeSpecification;
+use crate::ProberResult;
+use proc_macro2::TokenStream;
+use quote::quote_spanned;
+use syn::spanned::Spanned;
+
+pub(super) struct ProbeGenerator<'spec> {
+    spec: &'spec ProbeSpecification,
+}
+
+impl<'spec> ProbeGenerator<'spec> {
+    pub fn new(spec: &'spec ProbeSpecification) -> ProbeGenerator<'spec> {
+        ProbeGenerator { spec }
+    }
+
+    pub fn spec(&self) -> &ProbeSpecification {
+        self.spec
+    }
+
+    /// The lifetime parameters contributed by this probe's reference-typed arguments, threaded
+    /// through to the provider struct's own type parameters by
+    /// `ProviderGenerator::generate_provider_struct_type_params`.
+    pub fn args_lifetime_parameters(&self) -> Vec<syn::Lifetime> {
+        self.spec
+            .args()
+            .iter()
+            .filter_map(|arg| arg.lifetime().cloned())
+            .collect()
+    }
+
+    fn arg_types(&self) -> Vec<TokenStream> {
+        self.spec
+            .args()
+            .iter()
+            .map(|arg| arg.native_type_tokens())
+            .collect()
+    }
+
+    /// Declares this probe's `ProviderProbe` field in the provider impl struct `provider.rs`
+    /// generates.  `ProviderProbe` is what `is_enabled()`/`fire()` are actually implemented on; this
+    /// field is the one address both the manual `probeN_enabled()` accessor and the automatic guard
+    /// in `generate_trait_methods` read the semaphore from, so the two can never disagree.
+    pub fn generate_struct_member_declaration(&self) -> TokenStream {
+        let span = self.spec.item_fn().span();
+        let field = syn::Ident::new(self.spec.name(), span);
+        let arg_types = self.arg_types();
+
+        quote_spanned! { span =>
+            #field: ::probers::ProviderProbe<'a, (#(#arg_types,)*)>
+        }
+    }
+
+    /// Looks up this probe by name on the just-initialized `Provider`, to populate the field
+    /// `generate_struct_member_declaration` declared.
+    pub fn generate_struct_member_initialization(&self, provider_var: &syn::Ident) -> TokenStream {
+        let span = self.spec.item_fn().span();
+        let field = syn::Ident::new(self.spec.name(), span);
+        let name = self.spec.name();
+
+        quote_spanned! { span =>
+            #field: #provider_var
+                .probe(#name)
+                .expect(concat!("probe `", #name, "` was not registered"))
+        }
+    }
+
+    /// Registers this probe, and its argument types, with the `ProviderBuilder` assembling the
+    /// provider -- this is what actually causes the backend to allocate the semaphore `is_enabled()`
+    /// reads.
+    pub fn generate_add_probe_call(&self, builder: &syn::Ident) -> TokenStream {
+        let span = self.spec.item_fn().span();
+        let name = self.spec.name();
+        let arg_types = self.arg_types();
+
+        quote_spanned! { span =>
+            #builder.add_probe::<(#(#arg_types,)*)>(#name)?;
+        }
+    }
+
+    /// Generates the public method on the trait's struct that fires this probe.
+    ///
+    /// The body is wrapped in `if #field.is_enabled() { ... }`, so `fire()` is only called when
+    /// something is actually attached and listening. The check runs inside this method, after the
+    /// caller has evaluated the arguments it passes in: something expensive to compute
+    /// (`probe!(MyProbes::probe0(expensive_to_compute()))`) is still built when nothing is attached.
+    /// `provider.rs`'s `probeN_enabled()` exposes the same check for callers who want to skip
+    /// building such arguments themselves.
+    pub fn generate_trait_methods(
+        &self,
+        _trait_ident: &syn::Ident,
+        _provider_name: &str,
+        struct_type_path: &syn::Path,
+    ) -> ProberResult<TokenStream> {
+        let span = self.spec.item_fn().span();
+        let vis = &self.spec.item_fn().vis;
+        let sig = &self.spec.item_fn().sig;
+        let method_name = syn::Ident::new(self.spec.name(), span);
+        let field = syn::Ident::new(self.spec.name(), span);
+        let inputs = &sig.inputs;
+        let arg_names: Vec<&syn::Ident> =
+            self.spec.args().iter().map(|arg| arg.ident()).collect();
+
+        Ok(quote_spanned! { span =>
+            #[allow(dead_code)]
+            #vis fn #method_name(#inputs) {
+                if let Some(imp) = #struct_type_path::get() {
+                    if imp.#field.is_enabled() {
+                        imp.#field.fire((#(#arg_names,)*));
+                    }
+                }
+            }
+        })
+    }
+}
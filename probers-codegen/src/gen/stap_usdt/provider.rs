@@ -1,4 +1,5 @@
 use super::probe::ProbeGenerator;
+use crate::diagnostics::{Diagnostic, Diagnostics};
 use crate::provider::ProviderSpecification;
 use crate::ProberResult;
 use heck::{ShoutySnakeCase, SnakeCase};
@@ -23,37 +24,76 @@ impl<'spec> ProviderGenerator<'spec> {
     }
 
     pub fn generate(&self) -> ProberResult<TokenStream> {
+        // Problems found in individual probes (an unsupported argument type, a probe declared
+        // with a body, and so on) are collected here instead of bailing out of the whole macro on
+        // the first one, so a user fixing a 10-probe trait sees every problem, each underlined at
+        // its own span, in one compile rather than one-at-a-time.
+        let mut diagnostics = Diagnostics::new();
+
         // Re-generate this trait as a struct with our probing implementation in it
-        let prober_struct = self.generate_prober_struct()?;
+        let prober_struct = self.generate_prober_struct(&mut diagnostics)?;
 
         // Generate code for a struct and some `OnceCell` statics to hold the instance of the provider
         // and individual probe wrappers
         let impl_mod = self.generate_impl_mod();
 
+        // Consulting `is_empty()` rather than just always calling `render()` means a trait with no
+        // problems never even builds the (trivially empty, but still allocated) `compile_error!`
+        // token stream -- the one real use `is_empty()` has, since `render()`'s own output is a
+        // no-op either way.
+        let diagnostic_errors = if diagnostics.is_empty() {
+            TokenStream::new()
+        } else {
+            diagnostics.render()
+        };
+
         Ok(quote_spanned! { self.spec.item_trait().span() =>
             #prober_struct
 
             #impl_mod
+
+            #diagnostic_errors
         })
     }
     /// A provider is described by the user as a `trait`, with methods corresponding to probes.
     /// However it's actually implemented as a `struct` with no member fields, with static methods
     /// implementing the probes.  Thus, given as input the `trait`, we produce a `struct` of the same
     /// name whose implementation actually performs the firing of the probes.
-    fn generate_prober_struct(&self) -> ProberResult<TokenStream> {
+    fn generate_prober_struct(&self, diagnostics: &mut Diagnostics) -> ProberResult<TokenStream> {
         // From the probe specifications, generate the corresponding methods that will be on the probe
-        // struct.
+        // struct.  A probe that fails to generate (an unsupported argument type, a probe declared
+        // with a body, etc) doesn't abort the whole trait: its problem is recorded as a `Diagnostic`
+        // pointing at that probe specifically, and the remaining probes still generate normally, so
+        // a single compile reports every problem in the trait instead of just the first one found.
         let mut probe_methods: Vec<TokenStream> = Vec::new();
         let mod_name = self.get_provider_impl_mod_name();
         let struct_type_name = self.get_provider_impl_struct_type_name();
         let struct_type_path: syn::Path = parse_quote! { #mod_name::#struct_type_name };
         let provider_name = self.spec.name();
-        for probe in self.probes.iter() {
-            probe_methods.push(probe.generate_trait_methods(
+        for (index, probe) in self.probes.iter().enumerate() {
+            match probe.generate_trait_methods(
                 &self.spec.item_trait().ident,
                 &provider_name,
                 &struct_type_path,
-            )?);
+            ) {
+                Ok(methods) => {
+                    probe_methods.push(methods);
+
+                    // Expose the probe's semaphore as a cheap, manually-callable `probeN_enabled()`
+                    // so callers can gate expensive argument construction themselves; the generated
+                    // probe method checks the same semaphore only after its arguments are evaluated.
+                    match self.generate_is_enabled_method(probe, index) {
+                        Ok(method) => probe_methods.push(method),
+                        Err(e) => diagnostics.push(Diagnostic::new(
+                            probe.spec().item_fn().span(),
+                            e.to_string(),
+                        )),
+                    }
+                }
+                Err(e) => {
+                    diagnostics.push(Diagnostic::new(probe.spec().item_fn().span(), e.to_string()));
+                }
+            }
         }
 
         // Re-generate the trait method that we took as input, with the modifications to support
@@ -169,6 +209,39 @@ TODO: No other platforms supported yet
         Ok(result)
     }
 
+    /// Generates a `probeN_enabled() -> bool` method which reports whether anything is currently
+    /// attached to the given probe.  This lets a caller skip building expensive probe arguments of
+    /// their own accord; the generated probe method performs the same check before firing, but only
+    /// after its arguments have been evaluated.
+    ///
+    /// This backend wraps `libstapsdt`, which registers each probe (and the semaphore SystemTap/
+    /// `bpftrace` increment when they attach to it) itself via `stapsdt_provider_add_probe`, and
+    /// hands back a `ProviderProbe` that already knows how to read that semaphore. A second,
+    /// independently-allocated `AtomicU16` array here would never be the one address the attached
+    /// tracer is actually incrementing, so this just forwards to the real thing instead of
+    /// maintaining a parallel one.
+    fn generate_is_enabled_method(
+        &self,
+        probe: &ProbeGenerator,
+        _index: usize,
+    ) -> ProberResult<TokenStream> {
+        let span = probe.spec().item_fn().span();
+        let vis = &probe.spec().item_fn().vis;
+        let method_name = syn::Ident::new(&format!("{}_enabled", probe.spec().name()), span);
+        let probe_field = syn::Ident::new(probe.spec().name(), span);
+        let mod_name = self.get_provider_impl_mod_name();
+        let struct_type_name = self.get_provider_impl_struct_type_name();
+
+        Ok(quote_spanned! { span =>
+            #[allow(dead_code)]
+            #vis fn #method_name() -> bool {
+                #mod_name::#struct_type_name::get()
+                    .map(|imp| imp.#probe_field.is_enabled())
+                    .unwrap_or(false)
+            }
+        })
+    }
+
     /// The implementation of the probing logic is complex enough that it involves the declaration of a
     /// few variables and one new struct type.  All of this is contained within a module, to avoid the
     /// possibility of collissions with other code.  This method generates that module and all its
@@ -0,0 +1,139 @@
+//! Reads the configuration `build.rs` worked out (which `TracingType` to use, and any backend-
+//! specific options) back out of the environment, so the proc macros -- which run in a completely
+//! separate process from `build.rs` -- see the exact same decisions.
+use crate::error::TracersResult;
+use crate::spec::ProviderSpecification;
+use crate::TracingType;
+use std::env;
+
+/// Wraps the `TracingType` `build.rs` settled on.  Kept as its own type, rather than a bare
+/// `TracingType` field on `BuildInfo`, so that future build-time context specific to how that
+/// implementation was chosen (which probing library was found, at what version, ...) has somewhere
+/// to live without changing `BuildInfo`'s shape again.
+pub(crate) struct Implementation(TracingType);
+
+impl Implementation {
+    pub(crate) fn tracing_type(&self) -> TracingType {
+        self.0
+    }
+}
+
+/// The build-time configuration decided by `build.rs` and consumed by the proc macros via
+/// `code_generator()`.
+pub(crate) struct BuildInfo {
+    pub(crate) implementation: Implementation,
+
+    /// Whether the dynamic backend should resolve its native entry points at runtime via
+    /// `libloading` rather than linking the support library in at build time.  Set by
+    /// `TRACERS_DYNAMIC_RUNTIME_LOADING=1`, the macro-visible counterpart of whatever cfg/feature
+    /// `build.rs` used to make the same decision for itself.
+    runtime_loading: bool,
+
+    /// Whether `generate_native_code` should also emit a `<provider>.h` header declaring
+    /// C-callable macros for each probe, for mixed Rust/C++ applications.  Set by
+    /// `TRACERS_GENERATE_HEADER=1`.
+    generate_header: bool,
+
+    /// The providers discovered in the dependent crate's sources, used by `generate_native_code`
+    /// to decide what to emit (e.g. one header per provider).  `build.rs` populates this by
+    /// walking `targets` with `with_providers` before calling `generate_native_code`; the proc
+    /// macros, which only ever process one provider at a time via their own arguments, never need
+    /// it and leave it empty.
+    providers: Vec<ProviderSpecification>,
+
+    /// Where the dynamic backend's `NativeLoader` should `dlopen` the support library from, in
+    /// runtime-loaded mode. Set by `record_native_lib_path`, the same `cargo:rustc-env` channel
+    /// `record_tracing_type` uses, so a dependent crate can point this at a library it located
+    /// itself (e.g. via `pkg-config`) instead of relying on the dynamic linker's default search
+    /// path finding a bare soname.
+    native_lib_path: Option<String>,
+}
+
+/// The env var `record_tracing_type` writes and `BuildInfo::load` reads back, so `build.rs` and the
+/// proc macros -- two separate processes spawned during the same compilation -- see the identical
+/// decision. Cargo makes a `cargo:rustc-env=KEY=VALUE` line from a dependent crate's own `build.rs`
+/// visible to every rustc/proc-macro invocation compiling that same crate, which is exactly the
+/// channel this needs.
+const TRACING_TYPE_VAR: &str = "TRACERS_TRACING_TYPE";
+
+/// Records the `TracingType` a dependent crate's `build.rs` decided on (by whatever target/feature
+/// probing it does) so `BuildInfo::load()` -- running later, in the proc macros -- reaches the same
+/// conclusion instead of silently defaulting to `Disabled`.  The variable is also set in the
+/// current process, so a later `BuildInfo::load()` in the same build script sees it too.
+pub(crate) fn record_tracing_type(tracing_type: TracingType) {
+    let value = match tracing_type {
+        TracingType::Disabled => "disabled",
+        TracingType::Static => "static",
+        TracingType::Dynamic => "dynamic",
+        TracingType::PureRustUsdt => "pure_rust_usdt",
+    };
+    println!("cargo:rustc-env={}={}", TRACING_TYPE_VAR, value);
+    env::set_var(TRACING_TYPE_VAR, value);
+}
+
+/// The env var `record_native_lib_path` writes and `BuildInfo::load` reads back, carrying the
+/// path (or bare soname) the dynamic backend's `NativeLoader` should `dlopen` in runtime-loaded
+/// mode, for the same cross-process reason `TRACING_TYPE_VAR` exists.
+const NATIVE_LIB_PATH_VAR: &str = "TRACERS_NATIVE_LIB_PATH";
+
+/// Records where a dependent crate's `build.rs` located the native tracing support library (or
+/// simply the soname it wants `dlopen`ed via the dynamic linker's own search path), so the
+/// `dynamic` generator's `NativeLoader::load` call -- generated later, in the proc macros -- opens
+/// the same one instead of guessing.
+pub(crate) fn record_native_lib_path(path: &str) {
+    println!("cargo:rustc-env={}={}", NATIVE_LIB_PATH_VAR, path);
+}
+
+impl BuildInfo {
+    /// Loads the `BuildInfo` that `build.rs` recorded for this build.  Both a dependent crate's
+    /// build script (through `gen::generate_native_code`) and the proc macros call this, so they
+    /// can never disagree about which `CodeGenerator` is in effect.
+    ///
+    /// If nothing has called `record_tracing_type` yet -- e.g. an IDE's proc-macro expansion running
+    /// outside of a real `cargo build` -- this falls back to `Disabled` rather than guessing.
+    pub(crate) fn load() -> TracersResult<BuildInfo> {
+        let tracing_type = match env::var(TRACING_TYPE_VAR).as_deref() {
+            Ok("static") => TracingType::Static,
+            Ok("dynamic") => TracingType::Dynamic,
+            Ok("pure_rust_usdt") => TracingType::PureRustUsdt,
+            _ => TracingType::Disabled,
+        };
+
+        Ok(BuildInfo {
+            implementation: Implementation(tracing_type),
+            runtime_loading: env::var("TRACERS_DYNAMIC_RUNTIME_LOADING").is_ok(),
+            generate_header: env::var("TRACERS_GENERATE_HEADER").is_ok(),
+            providers: Vec::new(),
+            native_lib_path: env::var(NATIVE_LIB_PATH_VAR).ok(),
+        })
+    }
+
+    /// Attaches the providers `build.rs` discovered by scanning the dependent crate's sources, so
+    /// `generate_native_code` has them on hand without needing its own parsing pass.
+    pub(crate) fn with_providers(mut self, providers: Vec<ProviderSpecification>) -> BuildInfo {
+        self.providers = providers;
+        self
+    }
+
+    pub(crate) fn runtime_loading(&self) -> bool {
+        self.runtime_loading
+    }
+
+    pub(crate) fn generate_header(&self) -> bool {
+        self.generate_header
+    }
+
+    pub(crate) fn providers(&self) -> &[ProviderSpecification] {
+        &self.providers
+    }
+
+    /// Where `NativeLoader` should `dlopen` the native tracing support library from, in
+    /// runtime-loaded mode. Falls back to the bare soname so a build that never called
+    /// `record_native_lib_path` still resolves the library via the dynamic linker's own search
+    /// path, rather than failing to build the generated `dlopen` call at all.
+    pub(crate) fn native_lib_path(&self) -> &str {
+        self.native_lib_path
+            .as_deref()
+            .unwrap_or("libstapsdt.so")
+    }
+}
@@ -0,0 +1,83 @@
+//! Span-accurate compile-time diagnostics, shared by both the `tracers` (`#[tracer]`/`probe!`) and
+//! the legacy `probers` (`#[prober]`/`probe!`) macro crates -- `probers-codegen`'s own copy of this
+//! module was dropped in favor of re-exporting this one, since the two had drifted apart only in
+//! that the `probers-codegen` copy lacked `with_note`, not in anything that actually needed to
+//! differ between the two macro generations.
+//!
+//! Without this, a generator can only report a problem as a plain `Err` from the whole macro
+//! invocation, which `rustc` underlines at the call site of the macro itself -- not at the
+//! particular probe argument or provider item that was actually wrong. This lets a generator
+//! collect every problem it finds across a whole macro invocation, each carrying the `Span` of the
+//! offending token, and render them all at once as `compile_error!`s spliced into the output
+//! `TokenStream` via `quote_spanned!`. Rust still fails the build (a `compile_error!` is as fatal
+//! as a hard `Err` would have been) but every problem is underlined in its original location, in
+//! one pass, instead of one-at-a-time across repeated recompiles.
+use proc_macro2::{Span, TokenStream};
+use quote::quote_spanned;
+
+/// A single problem found while processing a provider trait or probe call, anchored to the span of
+/// the token that's actually wrong.
+pub struct Diagnostic {
+    span: Span,
+    message: String,
+    notes: Vec<(Span, String)>,
+}
+
+impl Diagnostic {
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
+        Diagnostic {
+            span,
+            message: message.into(),
+            notes: Vec::new(),
+        }
+    }
+
+    /// Attaches a secondary span with its own message, e.g. pointing at the provider trait a
+    /// malformed probe belongs to, or at a prior probe that already has this name. Rendered as its
+    /// own `compile_error!`, since stable Rust's `compile_error!` has no native concept of
+    /// note/help sub-spans the way rustc's own diagnostics do.
+    pub fn with_note(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.notes.push((span, message.into()));
+        self
+    }
+
+    fn render(&self) -> TokenStream {
+        let message = &self.message;
+        let mut tokens = quote_spanned! { self.span => compile_error!(#message); };
+
+        for (span, note) in &self.notes {
+            let note_message = format!("note: {}", note);
+            tokens.extend(quote_spanned! { *span => compile_error!(#note_message); });
+        }
+
+        tokens
+    }
+}
+
+/// Accumulates `Diagnostic`s across an entire macro invocation so a generator can keep processing
+/// after the first problem (e.g. checking every probe in a trait) instead of bailing out on the
+/// first one.
+#[derive(Default)]
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Diagnostics::default()
+    }
+
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    /// Renders every collected diagnostic as its own `compile_error!`, each at its own span, so
+    /// `rustc` reports and underlines all of them instead of just the first.
+    pub fn render(&self) -> TokenStream {
+        self.diagnostics.iter().map(Diagnostic::render).collect()
+    }
+}
@@ -0,0 +1,210 @@
+//! A `CodeGenerator` implementation that needs no C compiler, no native support library, and no
+//! work at all in `generate_native_code`.  Every probe is emitted as a standalone block of inline
+//! assembly containing the `.note.stapsdt` note that `bpftrace`/`perf`/`tplist`/DTrace's USDT
+//! reader all look for, following the convention first documented by SystemTap and adopted
+//! (informally, there's no spec) by every other USDT consumer.
+//!
+//! This is attractive because it has zero build-time dependencies -- the whole point of the
+//! `static`/`dynamic` generators is shelling out to a C toolchain to build a tiny wrapper library,
+//! and that's exactly what this generator avoids.  The tradeoff is that the note's byte layout has
+//! to be assembled by hand in the macro expansion, and every probe argument has to be described by
+//! a `SIZE@OPERAND` token in the note body rather than handed to a real function call.
+use super::{CodeGenerator, NativeLib};
+use crate::build_rs::BuildInfo;
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use crate::error::TracersResult;
+use crate::spec::{ProbeCallSpecification, ProviderInitSpecification, ProviderSpecification};
+use heck::SnakeCase;
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use syn::spanned::Spanned;
+use tracers_core::ProbeArgNativeType;
+
+/// SystemTap's own USDT note format caps the number of arguments a single probe can describe --
+/// SystemTap itself rejects a note with more -- so a trait with a probe beyond this arity has to be
+/// rejected here at macro-expansion time rather than producing a note no consumer can read.
+const MAX_USDT_ARGS: usize = 12;
+
+/// The `SIZE` half of a `.note.stapsdt` `SIZE@OPERAND` argument descriptor token: the byte width of
+/// the underlying native type, with a leading `-` for signed types (e.g. `-8` for `i64`, `4` for
+/// `u32`) per the convention SystemTap/`bpftrace` expect.
+trait AsmNoteSize {
+    fn asm_note_size(&self) -> i32;
+}
+
+impl AsmNoteSize for ProbeArgNativeType {
+    fn asm_note_size(&self) -> i32 {
+        match self {
+            ProbeArgNativeType::Char => 1,
+            ProbeArgNativeType::I8 => -1,
+            ProbeArgNativeType::I16 => -2,
+            ProbeArgNativeType::I32 => -4,
+            ProbeArgNativeType::I64 => -8,
+            ProbeArgNativeType::Isize => -(std::mem::size_of::<isize>() as i32),
+            ProbeArgNativeType::U8 => 1,
+            ProbeArgNativeType::U16 => 2,
+            ProbeArgNativeType::U32 => 4,
+            ProbeArgNativeType::U64 => 8,
+            ProbeArgNativeType::Usize => std::mem::size_of::<usize>() as i32,
+            ProbeArgNativeType::Bool => 1,
+            // Everything else (`&str`, `String`, pointers to wrapped/boxed values) is passed as a
+            // pointer-sized operand; the note reader dereferences it as a C string or struct as
+            // appropriate for the provider/probe it's attached to.
+            _ => std::mem::size_of::<usize>() as i32,
+        }
+    }
+}
+
+pub(crate) struct AsmUsdtGenerator {
+    _bi: BuildInfo,
+}
+
+impl AsmUsdtGenerator {
+    pub fn new(bi: BuildInfo) -> Self {
+        AsmUsdtGenerator { _bi: bi }
+    }
+
+    /// Computes the `SIZE@OPERAND` argument descriptor tokens that make up the note's argument
+    /// string.  `SIZE` is the byte width of the underlying native type, negative for signed types
+    /// (e.g. `-8` for `i64`, `4` for `u32`), and `OPERAND` is the `asm!` operand expression (e.g.
+    /// `{0}`) that the note refers the consumer to for that argument's value at probe time.
+    fn generate_arg_descriptor(call: &ProbeCallSpecification) -> String {
+        call.args()
+            .iter()
+            .enumerate()
+            .map(|(i, arg)| format!("{}@{{{}}}", arg.native_type().asm_note_size(), i))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl CodeGenerator for AsmUsdtGenerator {
+    fn handle_provider_trait(&self, provider: ProviderSpecification) -> TracersResult<TokenStream> {
+        // There's no struct of function pointers to build here; probes are free-standing `asm!`
+        // blocks generated directly at the call site in `handle_probe_call`, so all this has to do
+        // is re-emit the trait as a plain marker struct with no probe plumbing attached, the way
+        // the `static`/`dynamic` generators do for their own prober struct.
+        let span = provider.item_trait().span();
+        let ident = &provider.item_trait().ident;
+        let vis = &provider.item_trait().vis;
+        let attrs = &provider.item_trait().attrs;
+
+        // Collected here, across every probe in the trait, so a trait with several
+        // too-many-arguments probes gets all of them underlined in one compile instead of one at a
+        // time across repeated recompiles.
+        let mut diagnostics = Diagnostics::new();
+        for probe in provider.probes() {
+            if probe.args().len() > MAX_USDT_ARGS {
+                diagnostics.push(Diagnostic::new(
+                    span,
+                    format!(
+                        "probe `{}` has {} arguments, but a USDT note can only describe up to {}",
+                        probe.name(),
+                        probe.args().len(),
+                        MAX_USDT_ARGS
+                    ),
+                ));
+            }
+        }
+        // Consulting `is_empty()` rather than just always calling `render()` means a trait with no
+        // problems never even builds the (trivially empty, but still allocated) `compile_error!`
+        // token stream -- the one real use `is_empty()` has, since `render()`'s own output is a
+        // no-op either way.
+        let diagnostic_errors = if diagnostics.is_empty() {
+            TokenStream::new()
+        } else {
+            diagnostics.render()
+        };
+
+        Ok(quote_spanned! { span =>
+            #(#attrs)*
+            #vis struct #ident;
+
+            #diagnostic_errors
+        })
+    }
+
+    fn handle_probe_call(&self, call: ProbeCallSpecification) -> TracersResult<TokenStream> {
+        let span = call.span();
+        let provider_name = call.provider_name().to_snake_case();
+        let probe_name = call.probe_name().to_snake_case();
+        let arg_descriptor = Self::generate_arg_descriptor(&call);
+        let asm_args: Vec<TokenStream> = call
+            .args()
+            .iter()
+            .map(|arg| {
+                let expr = arg.value_expr();
+                quote! { in(reg) (#expr) }
+            })
+            .collect();
+
+        // This follows the canonical `STAP_PROBE_ASM` template from SystemTap's `sys/sdt.h` byte
+        // for byte: the ELF note's `namesz` bytes ("stapsdt\0", between labels 991 and 992) and
+        // `descsz` bytes (the three addresses plus provider/probe/args strings, between 993 and
+        // 994) both have to sit inside the *same* `.note.stapsdt` section as the note header they
+        // belong to -- a name emitted into `.rodata` instead is simply not part of the note at all,
+        // so a reader walking the note by `namesz` would misparse everything after it.
+        //
+        // `.stapsdt.base`'s `.ifndef`-guarded definition has to be repeated at every probe site
+        // (rather than emitted once for the whole crate) because each is a separate, independent
+        // `asm!` expansion; the guard just keeps repeated expansions in the same translation unit
+        // from redefining the weak symbol.
+        Ok(quote_spanned! { span =>
+            unsafe {
+                ::std::arch::asm!(
+                    "990: nop",
+                    ".pushsection .note.stapsdt,\"\",\"note\"",
+                    ".balign 4",
+                    ".4byte 992f-991f, 994f-993f, 3",
+                    "991: .asciz \"stapsdt\"",
+                    "992: .balign 4",
+                    "993:",
+                    ".8byte 990b",
+                    ".8byte _.stapsdt.base",
+                    ".8byte 0",
+                    concat!(".asciz \"", #provider_name, "\""),
+                    concat!(".asciz \"", #probe_name, "\""),
+                    concat!(".asciz \"", #arg_descriptor, "\""),
+                    "994: .balign 4",
+                    ".popsection",
+                    ".ifndef _.stapsdt.base",
+                    ".pushsection .stapsdt.base,\"aG\",\"progbits\",.stapsdt.base,comdat",
+                    ".weak _.stapsdt.base",
+                    ".hidden _.stapsdt.base",
+                    "_.stapsdt.base: .space 1",
+                    ".size _.stapsdt.base, 1",
+                    ".popsection",
+                    ".endif",
+                    #(#asm_args,)*
+                    options(nomem, nostack, preserves_flags),
+                );
+            }
+        })
+    }
+
+    fn handle_init_provider(
+        &self,
+        _init: ProviderInitSpecification,
+    ) -> TracersResult<TokenStream> {
+        // Nothing to initialize: there's no provider singleton, no `OnceCell`, and no lazy
+        // registration step.  The note is baked into the binary at compile time and the kernel or
+        // tracer discovers it by reading `.note.stapsdt` directly, so `init_provider!` is a no-op
+        // here just like it is when tracing is compiled out entirely.
+        Ok(quote! {})
+    }
+
+    fn generate_native_code(
+        &self,
+        _stdout: &mut dyn Write,
+        _manifest_dir: &Path,
+        _out_dir: &Path,
+        _package_name: &str,
+        _targets: Vec<PathBuf>,
+    ) -> Vec<NativeLib> {
+        // No C stub library, no support library, nothing to link.  The probes are entirely
+        // self-contained in the inline `asm!` blocks emitted by `handle_probe_call`.
+        Vec::new()
+    }
+}
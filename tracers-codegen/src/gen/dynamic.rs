@@ -0,0 +1,283 @@
+//! The dynamic tracing `CodeGenerator`.  Probes are fired through a `Tracer`/`Provider`/`Probe`
+//! trait object hierarchy (see `tracers_core::dynamic`) rather than through code generated
+//! per-probe, so most of what this generator does is wire up the lazily-initialized provider
+//! singleton and forward each `probe!` call to it.
+//!
+//! Resolving the underlying native tracing library can happen in one of two ways, selected by
+//! `BuildInfo::runtime_loading`:
+//!
+//! * Link-time (the default): `generate_native_code` tells `build.rs` to link the support library
+//!   (`libstapsdt`) directly, via the `NativeLib::DynamicSupportLib` variant, and the generated
+//!   code can assume the symbols are simply there.
+//! * Runtime-loaded: nothing is linked at build time at all.  Instead the generated `NativeLoader`
+//!   holds a `libloading::Library` plus one `Result<Symbol<...>, libloading::Error>` per native
+//!   entry point, resolved lazily the first time the provider initializes, inside the same
+//!   `OnceCell` every other init failure already flows through.  A missing library on the target
+//!   machine then shows up as an ordinary init failure -- reported through `get_init_error()` like
+//!   any other -- instead of refusing to link the binary at all, which is what makes it possible to
+//!   ship one binary across machines that may or may not have the tracing library installed.
+use super::{CodeGenerator, NativeLib};
+use crate::build_rs::BuildInfo;
+use crate::error::TracersResult;
+use crate::spec::{ProbeCallSpecification, ProviderInitSpecification, ProviderSpecification};
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use syn::spanned::Spanned;
+
+/// The native entry points the dynamic backend needs resolved, either at link time or, in
+/// runtime-loaded mode, by name via `libloading`, paired with the real C signature each one has so
+/// the resolved `Symbol` can actually be called instead of merely existing as an opaque `fn()`.
+fn native_entry_points() -> Vec<(&'static str, TokenStream)> {
+    vec![
+        (
+            "stapsdt_create_provider",
+            quote! { unsafe extern "C" fn(*const ::std::os::raw::c_char) -> *mut ::std::os::raw::c_void },
+        ),
+        (
+            "stapsdt_provider_add_probe",
+            quote! {
+                unsafe extern "C" fn(
+                    *mut ::std::os::raw::c_void,
+                    *const ::std::os::raw::c_char,
+                    ::std::os::raw::c_int,
+                    *const ::std::os::raw::c_int,
+                ) -> *mut ::std::os::raw::c_void
+            },
+        ),
+        (
+            "stapsdt_provider_load",
+            quote! { unsafe extern "C" fn(*mut ::std::os::raw::c_void) -> ::std::os::raw::c_int },
+        ),
+        (
+            "stapsdt_provider_unload",
+            quote! { unsafe extern "C" fn(*mut ::std::os::raw::c_void) -> ::std::os::raw::c_int },
+        ),
+        (
+            "stapsdt_fire_probe",
+            quote! { unsafe extern "C" fn(*mut ::std::os::raw::c_void, *const *const ::std::os::raw::c_void) },
+        ),
+    ]
+}
+
+pub(crate) struct DynamicGenerator {
+    bi: BuildInfo,
+}
+
+impl DynamicGenerator {
+    pub fn new(bi: BuildInfo) -> Self {
+        DynamicGenerator { bi }
+    }
+
+    /// The name of the private module generated alongside a provider trait to hold its singleton
+    /// state.  Both `handle_provider_trait` (which declares it) and `handle_probe_call`/
+    /// `handle_init_provider` (which have to refer back into it) need to compute the identical name
+    /// from nothing but the provider's own name, since each is invoked independently by a different
+    /// macro with no shared state between the calls.
+    fn impl_mod_name(provider_name: &str, span: Span) -> syn::Ident {
+        syn::Ident::new(
+            &format!("__{}_dynamic_impl", provider_name).to_lowercase(),
+            span,
+        )
+    }
+
+    /// Generates the loader struct used in runtime-loaded mode: one `libloading::Library` plus
+    /// one fallible `Symbol` per entry returned by `native_entry_points()`, built once behind the
+    /// provider's existing `OnceCell` init path.  Modeled on the same "struct of resolved-or-errored
+    /// function handles built in a fallible constructor" shape bindgen's `dynamic_library` ("dyngen")
+    /// mode generates, so a library that lacks one of the entry points just shows up as an `Err` on
+    /// the one symbol that failed to resolve.  A library that can't be opened at all makes `load`
+    /// itself return `Err`, which fails the provider's initialization.
+    ///
+    /// Each resolved symbol is stored with its lifetime widened to `'static` via `transmute`.  This
+    /// is the same trick every long-lived `libloading` wrapper relies on: it's sound only because
+    /// `_lib` is kept alongside the symbols in the same struct and is never dropped or moved out
+    /// from under them while a `Symbol` borrowed from it is still live.
+    fn generate_runtime_loader(&self, span: Span) -> TokenStream {
+        let symbol_fields: Vec<TokenStream> = native_entry_points()
+            .into_iter()
+            .map(|(name, fn_ty)| {
+                let field = syn::Ident::new(name, span);
+                quote_spanned! { span =>
+                    #[allow(dead_code)]
+                    #field: Result<::libloading::Symbol<'static, #fn_ty>, ::libloading::Error>
+                }
+            })
+            .collect();
+
+        let symbol_inits: Vec<TokenStream> = native_entry_points()
+            .into_iter()
+            .map(|(name, fn_ty)| {
+                let field = syn::Ident::new(name, span);
+                quote_spanned! { span =>
+                    #field: lib
+                        .get::<#fn_ty>(concat!(#name, "\0").as_bytes())
+                        .map(|s| unsafe {
+                            ::std::mem::transmute::<
+                                ::libloading::Symbol<#fn_ty>,
+                                ::libloading::Symbol<'static, #fn_ty>,
+                            >(s)
+                        })
+                }
+            })
+            .collect();
+
+        quote_spanned! { span =>
+            /// Holds the dynamically-loaded native tracing library along with each entry point
+            /// resolved (or not) by name.  A library missing one of the entry points shows up here
+            /// as `Err` on the affected symbol rather than as a link failure; a library that can't
+            /// be opened at all makes `load` return `Err` instead of a `NativeLoader`.
+            pub(super) struct NativeLoader {
+                #(#symbol_fields,)*
+                _lib: ::libloading::Library,
+            }
+
+            impl NativeLoader {
+                pub(super) fn load(path: &str) -> Result<NativeLoader, ::libloading::Error> {
+                    let lib = unsafe { ::libloading::Library::new(path)? };
+                    Ok(NativeLoader {
+                        #(#symbol_inits,)*
+                        _lib: lib,
+                    })
+                }
+            }
+        }
+    }
+}
+
+impl CodeGenerator for DynamicGenerator {
+    fn handle_provider_trait(&self, provider: ProviderSpecification) -> TracersResult<TokenStream> {
+        let span = provider.item_trait().span();
+        let ident = &provider.item_trait().ident;
+        let vis = &provider.item_trait().vis;
+        let attrs = &provider.item_trait().attrs;
+        let provider_name = provider.name();
+        let mod_name = Self::impl_mod_name(&provider_name, span);
+
+        let (loader_decl, init_expr) = if self.bi.runtime_loading() {
+            let loader_struct = self.generate_runtime_loader(span);
+            // Baked in directly from the already-loaded `BuildInfo` rather than via `env!()`:
+            // nothing sets a compile-time env var with this name, since `record_native_lib_path`
+            // (like `record_tracing_type`) only ever reaches the proc macros as a regular
+            // `cargo:rustc-env` var, read back here through `BuildInfo::load`, not re-looked-up at
+            // the generated call site.
+            let native_lib_path = self.bi.native_lib_path();
+            let init = quote_spanned! { span =>
+                static LOADER: OnceCell<Result<NativeLoader, ::libloading::Error>> = OnceCell::new();
+                match LOADER.get_or_init(|| NativeLoader::load(#native_lib_path)) {
+                    Ok(loader) => SystemTracer::define_provider_from_loader(&provider_name, loader),
+                    Err(e) => bail!(
+                        "failed to load the native tracing library at runtime: {}; \
+                         falling back to no-op tracing for this provider",
+                        e
+                    ),
+                }
+            };
+            (loader_struct, init)
+        } else {
+            let init = quote_spanned! { span =>
+                SystemTracer::define_provider(&provider_name, |builder| Ok(builder))
+            };
+            (quote_spanned! { span => }, init)
+        };
+
+        Ok(quote_spanned! { span =>
+            #(#attrs)*
+            #vis struct #ident;
+
+            #[allow(non_snake_case)]
+            mod #mod_name {
+                use ::tracers::runtime::failure::{bail, Fallible};
+                use ::tracers::runtime::dynamic::{Provider, SystemProvider, SystemTracer, Tracer};
+                use ::tracers::runtime::once_cell::sync::OnceCell;
+
+                #loader_decl
+
+                static PROVIDER: OnceCell<Fallible<SystemProvider>> = OnceCell::new();
+
+                pub(super) fn get_init_error() -> Option<&'static ::tracers::runtime::failure::Error> {
+                    PROVIDER.get().and_then(|fallible| fallible.as_ref().err())
+                }
+
+                pub(super) fn get() -> Option<&'static SystemProvider> {
+                    let provider_name = #provider_name;
+                    PROVIDER.get_or_init(|| #init_expr).as_ref().ok()
+                }
+            }
+
+            impl #ident {
+                #[allow(dead_code)]
+                #vis fn __try_init_provider() -> Option<&'static ::tracers::runtime::failure::Error> {
+                    #mod_name::get();
+                    #mod_name::get_init_error()
+                }
+
+                // `handle_probe_call`/`handle_init_provider` run as part of a separate macro
+                // invocation at the `probe!`/`init_provider!` call site, with no access to the
+                // private `#mod_name` module declared above -- only to whatever's public on
+                // `#ident` itself. This forwards into it so those call sites have something to
+                // call by name alone.
+                #[allow(dead_code)]
+                #vis fn __get_provider() -> Option<&'static ::tracers::runtime::dynamic::SystemProvider> {
+                    #mod_name::get()
+                }
+            }
+        })
+    }
+
+    fn handle_probe_call(&self, call: ProbeCallSpecification) -> TracersResult<TokenStream> {
+        let span = call.span();
+        let probe_name = call.probe_name();
+        let provider_ident = syn::Ident::new(call.provider_name(), span);
+        let args: Vec<TokenStream> = call.args().iter().map(|arg| arg.value_expr()).collect();
+
+        // Firing a probe is always just forwarding to whatever `SystemProvider` the singleton
+        // resolved at init time; whether that singleton was built from linked-in symbols or from
+        // `NativeLoader`'s resolved-at-runtime ones is entirely an init-time concern the probe call
+        // itself doesn't need to know about.
+        //
+        // `fire_probe` is called through its fully-qualified trait path rather than as a plain
+        // method call: this expansion lands at the `probe!` call site, in whatever module the
+        // caller wrote it in, which has no reason to have `use`d `Provider` itself -- that import
+        // only exists inside the private `#mod_name` module `handle_provider_trait` generates.
+        // The fully-qualified form resolves the method without needing the trait in scope there.
+        Ok(quote_spanned! { span =>
+            if let Some(provider) = #provider_ident::__get_provider() {
+                ::tracers::runtime::dynamic::Provider::fire_probe(
+                    provider,
+                    #probe_name,
+                    &[#(&(#args)),*],
+                );
+            }
+        })
+    }
+
+    fn handle_init_provider(
+        &self,
+        init: ProviderInitSpecification,
+    ) -> TracersResult<TokenStream> {
+        let span = init.span();
+        let provider_ident = syn::Ident::new(init.provider_name(), span);
+        Ok(quote_spanned! { span =>
+            #provider_ident::__try_init_provider()
+        })
+    }
+
+    fn generate_native_code(
+        &self,
+        _stdout: &mut dyn Write,
+        _manifest_dir: &Path,
+        _out_dir: &Path,
+        _package_name: &str,
+        _targets: Vec<PathBuf>,
+    ) -> Vec<NativeLib> {
+        if self.bi.runtime_loading() {
+            // Nothing to link: the library is found and loaded by the generated `NativeLoader` at
+            // runtime, so there's no `cargo:rustc-link-lib` to print here at all.
+            Vec::new()
+        } else {
+            vec![NativeLib::DynamicSupportLib("stapsdt".to_string())]
+        }
+    }
+}
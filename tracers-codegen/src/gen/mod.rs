@@ -12,6 +12,7 @@ use std::io::Write;
 use std::path::{Path, PathBuf};
 
 //mod c;
+pub(crate) mod asm_usdt;
 pub(crate) mod common;
 pub(crate) mod dynamic;
 pub(crate) mod r#static;
@@ -50,6 +51,12 @@ pub(crate) enum NativeLib {
     /// A path where support libs can be found.  This will be passed to cargo as
     /// one of the native library search paths
     SupportLibPath(PathBuf),
+
+    /// The path to a generated C/C++ header, written into `out_dir` by `generate_native_code`,
+    /// declaring macros that fire the same probes from non-Rust code in the same process.  This
+    /// isn't passed to cargo for linking; it exists purely so the caller (or a downstream build
+    /// script) can locate it and `#include` it from a mixed Rust/C++ application.
+    GeneratedHeaderPath(PathBuf),
 }
 
 /// Each probing implementation must implement this trait, which has components which are called at
@@ -91,10 +98,63 @@ pub(crate) trait CodeGenerator {
 pub(crate) fn code_generator() -> TracersResult<Box<dyn CodeGenerator>> {
     let bi = BuildInfo::load()?;
 
-    Ok(match bi.implementation.tracing_type() {
-        //There are two implementations: one for static tracing (`disabled` is a special case of
-        //`static`), and one for dynamic
+    Ok(code_generator_for(bi))
+}
+
+/// Picks the `CodeGenerator` implementation matching a `BuildInfo` that's already been loaded (and,
+/// for the `build.rs` driver below, already augmented with `with_providers`). Split out from
+/// `code_generator()` so that driver can load `bi` once, attach the providers it discovered, and
+/// still end up with the same generator the proc macros would have gotten from a bare `load()`.
+fn code_generator_for(bi: BuildInfo) -> Box<dyn CodeGenerator> {
+    match bi.implementation.tracing_type() {
+        //There are three implementations: one for static tracing (`disabled` is a special case of
+        //`static`), one for dynamic, and one which emits USDT probes directly as inline assembly
+        //with no native code generation step at all
         TracingType::Disabled | TracingType::Static => Box::new(r#static::StaticGenerator::new(bi)),
         TracingType::Dynamic => Box::new(dynamic::DynamicGenerator::new(bi)),
-    })
+        TracingType::PureRustUsdt => Box::new(asm_usdt::AsmUsdtGenerator::new(bi)),
+    }
+}
+
+/// Scans each of `targets` for `#[tracer]`-attributed traits and builds a `ProviderSpecification`
+/// for each one found. Parse failures and files with no provider traits are simply skipped -- this
+/// is only ever used to populate headers for providers that already compiled successfully, not to
+/// validate the crate, which is the `#[tracer]` macro's job.
+fn discover_providers(targets: &[PathBuf]) -> Vec<ProviderSpecification> {
+    targets
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .filter_map(|source| syn::parse_file(&source).ok())
+        .flat_map(|file| file.items)
+        .filter_map(|item| match item {
+            syn::Item::Trait(item_trait)
+                if item_trait.attrs.iter().any(|attr| attr.path.is_ident("tracer")) =>
+            {
+                ProviderSpecification::from_item_trait(item_trait).ok()
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The entry point a dependent crate's own `build.rs` calls, after it has already used
+/// `build_rs::record_tracing_type` to settle on (and record, for the proc macros) which
+/// `TracingType` this build uses. Loads that same `BuildInfo`, attaches whatever providers it finds
+/// by scanning `targets` (so `generate_header` has something to iterate), and runs the matching
+/// `CodeGenerator`'s native code generation step.
+pub(crate) fn generate_native_code(
+    stdout: &mut dyn Write,
+    manifest_dir: &Path,
+    out_dir: &Path,
+    package_name: &str,
+    targets: Vec<PathBuf>,
+) -> TracersResult<Vec<NativeLib>> {
+    let bi = BuildInfo::load()?;
+    let bi = if bi.generate_header() {
+        bi.with_providers(discover_providers(&targets))
+    } else {
+        bi
+    };
+
+    Ok(code_generator_for(bi).generate_native_code(stdout, manifest_dir, out_dir, package_name, targets))
 }
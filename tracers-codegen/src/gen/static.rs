@@ -0,0 +1,147 @@
+//! The static tracing `CodeGenerator`.  This is the default and most complete implementation: it
+//! builds a small C wrapper library around the platform's native USDT/SystemTap API and links it
+//! in at build time, via the `NativeLib::StaticWrapperLib`/`StaticWrapperLibPath` variants.
+//!
+//! `TracingType::Disabled` reuses this same generator rather than having its own, since "no probes
+//! fire" is just the degenerate case of the static wrapper lib never being linked at all -- the
+//! generated Rust-side code is identical either way, only `generate_native_code`'s behavior
+//! differs.
+use super::{CodeGenerator, NativeLib};
+use crate::build_rs::BuildInfo;
+use crate::error::TracersResult;
+use crate::spec::{ProbeCallSpecification, ProviderInitSpecification, ProviderSpecification};
+use crate::TracingType;
+use proc_macro2::TokenStream;
+use quote::quote_spanned;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use syn::spanned::Spanned;
+
+pub(crate) struct StaticGenerator {
+    bi: BuildInfo,
+}
+
+impl StaticGenerator {
+    pub fn new(bi: BuildInfo) -> Self {
+        StaticGenerator { bi }
+    }
+
+    /// Writes a `<provider>.h` header into `out_dir` declaring one `PROVIDER_PROBEN(...)` macro
+    /// per probe, with the same provider/probe names and argument order the Rust side uses.  A
+    /// mixed Rust/C++ application can `#include` this to fire probes from its C/C++ components
+    /// under the same USDT provider as the Rust-declared ones, which keeps one coherent probe
+    /// namespace per process instead of two that happen to look similar.
+    fn generate_header(
+        &self,
+        out_dir: &Path,
+        package_name: &str,
+        provider: &ProviderSpecification,
+    ) -> std::io::Result<PathBuf> {
+        let provider_name = provider.name();
+        let guard = format!("{}_PROBES_H", provider_name.to_uppercase());
+        let mut header = String::new();
+        header.push_str(&format!("/* Generated by tracers-codegen for {} -- do not edit */\n", package_name));
+        header.push_str(&format!("#ifndef {}\n#define {}\n\n", guard, guard));
+        header.push_str("#include <sys/sdt.h>\n\n");
+
+        for probe in provider.probes() {
+            let macro_name = format!(
+                "{}_{}",
+                provider_name.to_uppercase(),
+                probe.name().to_uppercase()
+            );
+            let arity = probe.args().len();
+            let params: Vec<String> = (0..arity).map(|i| format!("arg{}", i)).collect();
+            let params = params.join(", ");
+            header.push_str(&format!(
+                "#define {macro}({params}) STAP_PROBE{arity}({provider}, {probe}{sep}{params})\n",
+                macro = macro_name,
+                params = params,
+                arity = arity,
+                provider = provider_name,
+                probe = probe.name(),
+                sep = if arity > 0 { ", " } else { "" },
+            ));
+        }
+
+        header.push_str(&format!("\n#endif /* {} */\n", guard));
+
+        let path = out_dir.join(format!("{}.h", provider_name));
+        fs::write(&path, header)?;
+        Ok(path)
+    }
+}
+
+impl CodeGenerator for StaticGenerator {
+    fn handle_provider_trait(&self, provider: ProviderSpecification) -> TracersResult<TokenStream> {
+        let span = provider.item_trait().span();
+        let ident = &provider.item_trait().ident;
+        let vis = &provider.item_trait().vis;
+        let attrs = &provider.item_trait().attrs;
+
+        Ok(quote_spanned! { span =>
+            #(#attrs)*
+            #vis struct #ident;
+        })
+    }
+
+    fn handle_probe_call(&self, call: ProbeCallSpecification) -> TracersResult<TokenStream> {
+        let span = call.span();
+        let provider_name = call.provider_name();
+        let probe_name = call.probe_name();
+        let args: Vec<TokenStream> = call.args().iter().map(|arg| arg.value_expr()).collect();
+
+        Ok(quote_spanned! { span =>
+            ::tracers::runtime::r#static::fire_probe(#provider_name, #probe_name, &[#(&(#args)),*]);
+        })
+    }
+
+    fn handle_init_provider(
+        &self,
+        init: ProviderInitSpecification,
+    ) -> TracersResult<TokenStream> {
+        let span = init.span();
+        let provider_name = init.provider_name();
+        Ok(quote_spanned! { span =>
+            ::tracers::runtime::r#static::init_provider(#provider_name);
+        })
+    }
+
+    fn generate_native_code(
+        &self,
+        stdout: &mut dyn Write,
+        _manifest_dir: &Path,
+        out_dir: &Path,
+        package_name: &str,
+        _targets: Vec<PathBuf>,
+    ) -> Vec<NativeLib> {
+        // With tracing disabled there is no wrapper lib to link and no header to emit.
+        if self.bi.implementation.tracing_type() == TracingType::Disabled {
+            return Vec::new();
+        }
+
+        let mut libs = vec![
+            NativeLib::StaticWrapperLib(package_name.to_string()),
+            NativeLib::StaticWrapperLibPath(out_dir.to_path_buf()),
+        ];
+
+        if self.bi.generate_header() {
+            for provider in self.bi.providers() {
+                match self.generate_header(out_dir, package_name, provider) {
+                    Ok(path) => libs.push(NativeLib::GeneratedHeaderPath(path)),
+                    Err(e) => {
+                        let _ = writeln!(
+                            stdout,
+                            "warning: failed to write C header for provider `{}`: {}",
+                            provider.name(),
+                            e
+                        );
+                    }
+                }
+            }
+        }
+
+        libs
+    }
+}
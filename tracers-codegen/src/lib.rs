@@ -0,0 +1,27 @@
+//! Shared crate-root definitions used by every code generator under `gen`.
+
+pub(crate) mod build_rs;
+/// Public so the legacy `probers-codegen` crate can share this implementation instead of
+/// maintaining its own near-identical copy.
+pub mod diagnostics;
+pub(crate) mod gen;
+
+/// Which tracing implementation a particular build of `tracers` has been configured to use.
+/// Decided by the dependent crate's `build.rs`, recorded with `build_rs::record_tracing_type`, read
+/// back by `build_rs::BuildInfo::load`, and used by `gen::code_generator()` to pick the matching
+/// `CodeGenerator` impl.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TracingType {
+    /// Tracing is compiled out entirely; probes are no-ops.
+    Disabled,
+
+    /// Probes are implemented via a generated C wrapper library, linked in at build time.
+    Static,
+
+    /// Probes are implemented via the `tracers_core::dynamic` trait-object hierarchy.
+    Dynamic,
+
+    /// Probes are implemented directly as inline assembly emitting `.note.stapsdt` notes, with no
+    /// native code generation step and no C stub library.
+    PureRustUsdt,
+}
@@ -0,0 +1,23 @@
+use std::env;
+
+/// Turns the `links` metadata `libstapsdt-sys`'s `build.rs` prints on unsupported targets into the
+/// `dyn_stap_enabled`/`dyn_noop_enabled` cfg flags this crate's `runtime::dynamic` module actually
+/// switches on.
+///
+/// `cargo:unsupported=1`, printed by a build script with a `links = "stapsdt"` manifest key,
+/// surfaces to every crate with a build-time dependency on it as `DEP_STAPSDT_UNSUPPORTED`. Reading
+/// it here and re-emitting it as a `rustc-cfg` is what actually lets `SystemTracer` fall back to
+/// `NoOpTracer` on a target libstapsdt-sys declined to build for, instead of the `DEP_*` var simply
+/// going unread.
+fn main() {
+    println!("cargo:rerun-if-env-changed=DEP_STAPSDT_UNSUPPORTED");
+
+    println!("cargo:rustc-cfg=enabled");
+    println!("cargo:rustc-cfg=dynamic_enabled");
+
+    if env::var("DEP_STAPSDT_UNSUPPORTED").is_ok() {
+        println!("cargo:rustc-cfg=dyn_noop_enabled");
+    } else {
+        println!("cargo:rustc-cfg=dyn_stap_enabled");
+    }
+}
@@ -27,11 +27,18 @@ pub mod runtime {
         // it makes more sense to a caller to deal with, for example, `tracers::Provider`
 
         //Alias `SystemTracer` to the appropriate implementation based on the determination made in
-        //`build.rs`
-        #[cfg(dyn_stap_enabled)]
+        //`build.rs`.
+        //
+        //The `force-noop` feature overrides that autodetected choice unconditionally, so users who
+        //need a reproducible build (or who just want to rule out the tracing backend while
+        //debugging something else) have a supported way to force `NoOpTracer` regardless of what
+        //probing libraries `build.rs` found on the host.  Additional backends can plug into this
+        //same cascade by adding their own `dyn_*_enabled` cfg and a corresponding feature, without
+        //having to rewrite the selection logic for the ones that came before.
+        #[cfg(all(dyn_stap_enabled, not(feature = "force-noop")))]
         pub type SystemTracer = tracers_dyn_stap::StapTracer;
 
-        #[cfg(dyn_noop_enabled)]
+        #[cfg(any(dyn_noop_enabled, feature = "force-noop"))]
         pub type SystemTracer = tracers_noop::NoOpTracer;
 
         #[cfg(dynamic_enabled)]
@@ -50,7 +57,7 @@ mod test {
     use tracers_core::dynamic::Tracer;
 
     #[test]
-    #[cfg(dynamic_enabled)]
+    #[cfg(all(dynamic_enabled, not(feature = "force-noop")))]
     fn verify_expected_dynamic_tracing_impl() {
         //This very simple test checks the TRACERS_EXPECTED_DYNAMIC_IMPL env var, and if set, asserts that
         //the tracing implementation compiled into this library matches the expected one.  In
@@ -61,6 +68,19 @@ mod test {
         }
     }
 
+    #[test]
+    #[cfg(all(dynamic_enabled, feature = "force-noop"))]
+    fn verify_expected_dynamic_tracing_impl() {
+        //`force-noop` unconditionally overrides whatever backend `build.rs` autodetected, so the
+        //CI-set expectation (which reflects that autodetection) no longer holds -- the compiled
+        //impl is always `NoOpTracer` here regardless of what the host environment would otherwise
+        //have selected.
+        assert_eq!(
+            dynamic::SystemTracer::TRACING_IMPLEMENTATION,
+            tracers_noop::NoOpTracer::TRACING_IMPLEMENTATION
+        );
+    }
+
     #[test]
     #[cfg(not(dynamic_enabled))]
     fn verify_expected_dynamic_tracing_impl() {